@@ -1,5 +1,7 @@
 //! ABI related utilities
 
+pub mod codegen;
+
 use ethabi::param_type::{ParamType, Reader};
 use ethabi::token::{LenientTokenizer, StrictTokenizer, Token, Tokenizer};
 use ethabi::{decode, encode};
@@ -7,8 +9,10 @@ use hex::{FromHex, ToHex};
 use keys::Address;
 use proto::core::{
     SmartContract_ABI_Entry as AbiEntry, SmartContract_ABI_Entry_EntryType as AbiEntryType,
+    SmartContract_ABI_Entry_Param as AbiEntryParam,
     SmartContract_ABI_Entry_StateMutabilityType as StateMutabilityType,
 };
+use protobuf::RepeatedField;
 use std::fmt::Write as FmtWrite;
 
 use crate::error::Error;
@@ -22,20 +26,46 @@ pub fn fnhash(fname: &str) -> [u8; 4] {
     hash_code
 }
 
+/// Reads a Solidity type string into a `ParamType`, remapping the TRON-specific
+/// `trcToken` type onto `uint256`. The substitution is textual, so it also
+/// applies to `trcToken` appearing inside a tuple type, e.g. `(trcToken,address)`.
+fn read_param_type(field_type: &str) -> Result<ParamType, Error> {
+    Ok(Reader::read(&field_type.replace("trcToken", "uint256"))?)
+}
+
+/// Renders a `keys::Address` the way `encode_params`/ethabi's
+/// `ParamType::Address` tokenizer expects: `0x`-prefixed hex of the 20-byte
+/// TVM/EVM-compatible payload. This is *not* the same as `Address`'s
+/// `Display` impl, which renders the base58 TRON representation -- encoding
+/// a base58 string directly against `ParamType::Address` is wrong, since
+/// ethabi has no notion of TRON's address format.
+///
+/// NOTE: `keys` isn't vendored in this tree, so `as_tvm_bytes` (the mirror
+/// of the `from_tvm_bytes` constructor already used below) is assumed
+/// rather than confirmed; double check the accessor name against the
+/// `keys` crate before relying on this in production.
+pub(crate) fn address_to_abi_hex(addr: &Address) -> String {
+    format!("0x{}", addr.as_tvm_bytes().encode_hex::<String>())
+}
+
 // ref: https://github.com/paritytech/ethabi/blob/master/cli/src/main.rs
+///
+/// `bytes`/`bytesN` values are always tokenized from `0x`-prefixed (or bare)
+/// hex, distinct from `uint8[]`, which is tokenized as a decimal array like
+/// `[1, 2, 3]` -- the two must not be conflated when encoding calldata for
+/// methods such as TRC20-style `transfer(address,bytes)`. An odd-length hex
+/// string for a top-level `bytes`/`bytesN` argument is rejected rather than
+/// silently truncated; a `bytes` field nested inside a tuple or array isn't
+/// separately validated here and is left to the tokenizer.
 pub fn encode_params(types: &[&str], values: &[String]) -> Result<Vec<u8>, Error> {
     assert_eq!(types.len(), values.len());
 
-    let types: Vec<ParamType> = types
-        .iter()
-        .map(|&s| {
-            if s == "trcToken" {
-                Reader::read("uint256")
-            } else {
-                Reader::read(s)
-            }
-        })
-        .collect::<Result<_, _>>()?;
+    let types: Vec<ParamType> = types.iter().map(|&s| read_param_type(s)).collect::<Result<_, _>>()?;
+    for (param_type, value) in types.iter().zip(values.iter()) {
+        if matches!(param_type, ParamType::Bytes | ParamType::FixedBytes(_)) {
+            validate_bytes_hex(value)?;
+        }
+    }
     let params: Vec<_> = types.into_iter().zip(values.iter().map(|v| v as &str)).collect();
 
     let tokens = parse_tokens(&params, true)?;
@@ -44,17 +74,11 @@ pub fn encode_params(types: &[&str], values: &[String]) -> Result<Vec<u8>, Error
     Ok(result.to_vec())
 }
 
+/// See `encode_params` for how `bytes`/`bytesN` are disambiguated from
+/// `uint8[]`: the former renders as `0x`-prefixed hex, the latter as a
+/// decimal array.
 pub fn decode_params(types: &[&str], data: &str) -> Result<Vec<String>, Error> {
-    let types: Vec<ParamType> = types
-        .iter()
-        .map(|&s| {
-            if s == "trcToken" {
-                Reader::read("uint256")
-            } else {
-                Reader::read(s)
-            }
-        })
-        .collect::<Result<_, _>>()?;
+    let types: Vec<ParamType> = types.iter().map(|&s| read_param_type(s)).collect::<Result<_, _>>()?;
     let data: Vec<u8> = Vec::from_hex(data)?;
     let tokens = decode(&types, &data)?;
 
@@ -63,6 +87,96 @@ pub fn decode_params(types: &[&str], data: &str) -> Result<Vec<String>, Error> {
     Ok(tokens.iter().map(pformat_abi_token).collect())
 }
 
+/// Validates that `value` is an unambiguous hex encoding of a `bytes`/`bytesN`
+/// argument, rejecting odd-length hex (which cannot be split into whole
+/// bytes) and non-hex digits with a descriptive error instead of letting the
+/// tokenizer truncate or misinterpret it.
+fn validate_bytes_hex(value: &str) -> Result<(), Error> {
+    let hex_str = value.strip_prefix("0x").unwrap_or(value);
+    if hex_str.is_empty() {
+        return Ok(());
+    }
+    Vec::<u8>::from_hex(hex_str)
+        .map(|_| ())
+        .map_err(|e| Error::Runtime(format!("ambiguous or invalid `bytes` value {:?}: {}", value, e)))
+}
+
+/// Decodes an event log, splitting indexed arguments (read from `topics`)
+/// from non-indexed arguments (packed into `data`).
+///
+/// `topics[0]` must be the keccak256 hash of the event signature
+/// (`entry_to_method_name(entry)`); the remaining topics hold one indexed
+/// argument each, in declaration order. For dynamic indexed types (`string`,
+/// `bytes`, arrays, tuples) Solidity stores only the keccak256 hash of the
+/// value in the topic, so those are surfaced as a hash marker rather than
+/// decoded.
+pub fn decode_event(entry: &AbiEntry, topics: &[Vec<u8>], data: &str) -> Result<Vec<(String, String)>, Error> {
+    let sig_hash = topics
+        .first()
+        .ok_or_else(|| Error::Runtime("event log has no topics".to_owned()))?;
+    let expected_sig_hash = crypto::keccak256(entry_to_method_name(entry).as_bytes());
+    if sig_hash.as_slice() != &expected_sig_hash[..] {
+        return Err(Error::Runtime(format!(
+            "event signature mismatch: expected {}, got {}",
+            hex::encode(&expected_sig_hash[..]),
+            hex::encode(sig_hash),
+        )));
+    }
+    let indexed_topics = &topics[1..];
+
+    let inputs = entry.get_inputs();
+    let num_indexed = inputs.iter().filter(|arg| arg.get_indexed()).count();
+    if num_indexed != indexed_topics.len() {
+        return Err(Error::Runtime(format!(
+            "event {} declares {} indexed argument(s), but {} topic(s) were given",
+            entry.get_name(),
+            num_indexed,
+            indexed_topics.len(),
+        )));
+    }
+
+    let data_types = inputs
+        .iter()
+        .filter(|arg| !arg.get_indexed())
+        .map(|arg| arg.get_field_type())
+        .collect::<Vec<_>>();
+    let mut decoded_data = decode_params(&data_types, data)?.into_iter();
+    let mut indexed_topics = indexed_topics.iter();
+
+    inputs
+        .iter()
+        .map(|arg| {
+            let value = if arg.get_indexed() {
+                let topic = indexed_topics.next().expect("checked above");
+                let param_type = read_param_type(arg.get_field_type())?;
+                if is_dynamic_param_type(&param_type) {
+                    format!("keccak256({})", hex::encode(topic))
+                } else {
+                    let token = decode(&[param_type], topic)?
+                        .pop()
+                        .ok_or_else(|| Error::Runtime(format!("empty indexed topic for {}", arg.get_name())))?;
+                    pformat_abi_token(&token)
+                }
+            } else {
+                decoded_data.next().expect("checked above")
+            };
+            Ok((arg.get_name().to_owned(), value))
+        })
+        .collect()
+}
+
+/// Whether a topic for this type holds a keccak256 hash of the value rather
+/// than the value itself (ABI encoder rule for indexed event arguments). This
+/// includes `FixedArray` (e.g. `uint8[3]`): fixed-size arrays are hashed into
+/// their topic exactly like dynamic arrays, only their byte length is known
+/// upfront.
+fn is_dynamic_param_type(pt: &ParamType) -> bool {
+    matches!(
+        pt,
+        ParamType::String | ParamType::Bytes | ParamType::Array(_) | ParamType::FixedArray(_, _) | ParamType::Tuple(_)
+    )
+}
+
 fn parse_tokens(params: &[(ParamType, &str)], lenient: bool) -> Result<Vec<Token>, Error> {
     params
         .iter()
@@ -74,6 +188,9 @@ fn parse_tokens(params: &[(ParamType, &str)], lenient: bool) -> Result<Vec<Token
         .map_err(From::from)
 }
 
+/// Formats a decoded token. `bytes`/`bytesN` always render as `0x`-prefixed
+/// hex, distinct from a `uint8[]` array, which renders as a decimal array
+/// (`Token::Array` of `Token::Uint`) -- see `encode_params`.
 fn pformat_abi_token(tok: &Token) -> String {
     match tok {
         Token::Address(raw) => Address::from_tvm_bytes(raw.as_ref()).to_string(),
@@ -81,13 +198,55 @@ fn pformat_abi_token(tok: &Token) -> String {
         Token::Uint(val) => val.to_string(),
         Token::Bool(val) => val.to_string(),
         Token::Array(val) => format!("[{}]", val.iter().map(pformat_abi_token).collect::<Vec<_>>().join(", ")),
-        Token::Bytes(val) => val.encode_hex::<String>(),
-        Token::FixedBytes(val) => hex::encode(&val),
-        Token::Tuple(_) => "tuple(...)".into(),
+        Token::Bytes(val) => format!("0x{}", val.encode_hex::<String>()),
+        Token::FixedBytes(val) => format!("0x{}", hex::encode(&val)),
+        Token::Tuple(val) => format!("({})", val.iter().map(pformat_abi_token).collect::<Vec<_>>().join(", ")),
         ref t => format!("{:?}", t),
     }
 }
 
+/// Like `pformat_abi_token`, but renders into a structured `serde_json::Value`
+/// instead of a flattened string: addresses become base58 strings, uints
+/// become decimal strings (to avoid precision loss in JSON numbers), bytes
+/// become `0x`-prefixed hex strings, and arrays/tuples become nested JSON
+/// values.
+fn pformat_abi_token_json(tok: &Token) -> serde_json::Value {
+    use serde_json::Value;
+
+    match tok {
+        Token::Address(raw) => Value::String(Address::from_tvm_bytes(raw.as_ref()).to_string()),
+        Token::String(s) => Value::String(s.clone()),
+        Token::Uint(val) => Value::String(val.to_string()),
+        Token::Int(val) => Value::String(val.to_string()),
+        Token::Bool(val) => Value::Bool(*val),
+        Token::Array(val) => Value::Array(val.iter().map(pformat_abi_token_json).collect()),
+        Token::Bytes(val) => Value::String(format!("0x{}", val.encode_hex::<String>())),
+        Token::FixedBytes(val) => Value::String(format!("0x{}", hex::encode(val))),
+        Token::Tuple(val) => {
+            let mut obj = serde_json::Map::with_capacity(val.len());
+            for (i, member) in val.iter().enumerate() {
+                obj.insert(i.to_string(), pformat_abi_token_json(member));
+            }
+            Value::Object(obj)
+        }
+        ref t => Value::String(format!("{:?}", t)),
+    }
+}
+
+/// Like `decode_params`, but returns each decoded value as a structured
+/// `serde_json::Value` (see `pformat_abi_token_json`) instead of a flattened
+/// string, so callers can consume nested tuples/arrays from modern contracts
+/// without losing structure.
+pub fn decode_params_json(types: &[&str], data: &str) -> Result<Vec<serde_json::Value>, Error> {
+    let param_types: Vec<ParamType> = types.iter().map(|&s| read_param_type(s)).collect::<Result<_, _>>()?;
+    let data: Vec<u8> = Vec::from_hex(data)?;
+    let tokens = decode(&param_types, &data)?;
+
+    assert_eq!(types.len(), tokens.len());
+
+    Ok(tokens.iter().map(pformat_abi_token_json).collect())
+}
+
 pub fn entry_to_method_name(entry: &AbiEntry) -> String {
     format!(
         "{}({})",
@@ -165,3 +324,265 @@ pub fn entry_to_input_types(entry: &AbiEntry) -> Vec<&str> {
         .map(|arg| arg.get_field_type())
         .collect::<Vec<_>>()
 }
+
+/// Parses a single Solidity-style declaration, the inverse of
+/// `entry_to_method_name_pretty`, e.g.:
+///
+/// - `function transfer(address to, uint256 amount) returns (bool)`
+/// - `event Transfer(address indexed from, address indexed to, uint256 value)`
+/// - `constructor(uint256 supply) payable`
+pub fn parse_abi_entry(sig: &str) -> Result<AbiEntry, Error> {
+    let sig = sig.trim();
+    let (keyword, rest) = split_first_token(sig).ok_or_else(|| Error::Runtime("empty ABI declaration".to_owned()))?;
+
+    let field_type = match keyword {
+        // `entry_to_method_name_pretty` renders a fallback as a nameless `function(...)`.
+        "function" if rest.trim_start().starts_with('(') => AbiEntryType::Fallback,
+        "function" => AbiEntryType::Function,
+        "event" => AbiEntryType::Event,
+        "constructor" => AbiEntryType::Constructor,
+        "fallback" => AbiEntryType::Fallback,
+        other => return Err(Error::Runtime(format!("unknown ABI entry keyword {:?}", other))),
+    };
+
+    let rest = rest.trim_start();
+    let (name, rest) = if field_type == AbiEntryType::Constructor || field_type == AbiEntryType::Fallback {
+        ("", rest)
+    } else {
+        split_first_token(rest).ok_or_else(|| Error::Runtime(format!("missing name in {:?}", sig)))?
+    };
+
+    let rest = rest.trim_start();
+    if !rest.starts_with('(') {
+        return Err(Error::Runtime(format!("expected `(` after name in {:?}", sig)));
+    }
+    let (args_str, rest) = split_balanced_parens(rest)?;
+    let inputs = args_str
+        .split_top_level_commas()
+        .filter(|arg| !arg.trim().is_empty())
+        .map(parse_entry_param)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut payable = false;
+    let mut state_mutability = None;
+    let mut outputs = Vec::new();
+
+    let mut rest = rest.trim_start();
+    loop {
+        if let Some(next) = rest.strip_prefix("payable") {
+            payable = true;
+            rest = next.trim_start();
+        } else if let Some(next) = rest.strip_prefix("view") {
+            state_mutability = Some(StateMutabilityType::View);
+            rest = next.trim_start();
+        } else if let Some(next) = rest.strip_prefix("returns") {
+            let next = next.trim_start();
+            if !next.starts_with('(') {
+                return Err(Error::Runtime(format!("expected `(` after `returns` in {:?}", sig)));
+            }
+            let (returns_str, next) = split_balanced_parens(next)?;
+            outputs = returns_str
+                .split_top_level_commas()
+                .filter(|arg| !arg.trim().is_empty())
+                .map(parse_entry_param)
+                .collect::<Result<Vec<_>, _>>()?;
+            rest = next.trim_start();
+        } else if rest.is_empty() {
+            break;
+        } else {
+            return Err(Error::Runtime(format!("unexpected trailing tokens {:?} in {:?}", rest, sig)));
+        }
+    }
+
+    let mut entry = AbiEntry::new();
+    entry.set_field_type(field_type);
+    entry.set_name(name.to_owned());
+    entry.set_inputs(RepeatedField::from_vec(inputs));
+    entry.set_outputs(RepeatedField::from_vec(outputs));
+    entry.set_payable(payable);
+    if let Some(state_mutability) = state_mutability {
+        entry.set_stateMutability(state_mutability);
+    }
+    Ok(entry)
+}
+
+/// Parses one declaration per non-empty, non-comment line.
+pub fn parse_abi(abi: &str) -> Result<Vec<AbiEntry>, Error> {
+    abi.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("//"))
+        .map(parse_abi_entry)
+        .collect()
+}
+
+fn parse_entry_param(arg: &str) -> Result<AbiEntryParam, Error> {
+    let tokens = arg.split_whitespace().collect::<Vec<_>>();
+    let (field_type, indexed, name) = match tokens.as_slice() {
+        [ty] => (*ty, false, ""),
+        [ty, "indexed"] => (*ty, true, ""),
+        [ty, name] => (*ty, false, *name),
+        [ty, "indexed", name] => (*ty, true, *name),
+        _ => return Err(Error::Runtime(format!("malformed ABI argument {:?}", arg))),
+    };
+
+    let mut param = AbiEntryParam::new();
+    param.set_field_type(field_type.to_owned());
+    param.set_name(name.to_owned());
+    param.set_indexed(indexed);
+    Ok(param)
+}
+
+/// Splits off the leading identifier in `s`, i.e. the keyword
+/// (`function`/`event`/`constructor`/`fallback`) or the name that follows it.
+/// Solidity puts `(` flush against both (`constructor(uint256 supply)`,
+/// `transfer(address to,...)`), so the identifier ends at the first of `(`
+/// *or* whitespace, not whitespace alone.
+fn split_first_token(s: &str) -> Option<(&str, &str)> {
+    let s = s.trim_start();
+    let end = s.find(|ch: char| ch.is_whitespace() || ch == '(').unwrap_or(s.len());
+    if end == 0 {
+        return None;
+    }
+    Some((&s[..end], &s[end..]))
+}
+
+/// Splits off the text inside the leading balanced `(...)` group of `s`,
+/// honoring nested parentheses (used by tuple types), and returns it along
+/// with whatever follows the closing paren.
+fn split_balanced_parens(s: &str) -> Result<(&str, &str), Error> {
+    assert!(s.starts_with('('));
+    let mut depth = 0i32;
+    for (i, ch) in s.char_indices() {
+        match ch {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok((&s[1..i], &s[i + 1..]));
+                }
+            }
+            _ => (),
+        }
+    }
+    Err(Error::Runtime(format!("unbalanced parentheses in {:?}", s)))
+}
+
+trait SplitTopLevelCommas {
+    fn split_top_level_commas(&self) -> std::vec::IntoIter<&str>;
+}
+
+impl SplitTopLevelCommas for str {
+    fn split_top_level_commas(&self) -> std::vec::IntoIter<&str> {
+        let mut parts = Vec::new();
+        let mut depth = 0i32;
+        let mut start = 0;
+        for (i, ch) in self.char_indices() {
+            match ch {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                ',' if depth == 0 => {
+                    parts.push(self[start..i].trim());
+                    start = i + 1;
+                }
+                _ => (),
+            }
+        }
+        parts.push(self[start..].trim());
+        parts.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_decode_bytes_roundtrips_as_hex() {
+        let calldata = encode_params(&["bytes"], &["0xdeadbeef".to_owned()]).unwrap();
+        let decoded = decode_params(&["bytes"], &hex::encode(&calldata)).unwrap();
+        assert_eq!(decoded[0], "0xdeadbeef");
+    }
+
+    #[test]
+    fn encode_decode_uint8_array_roundtrips_as_decimal() {
+        let calldata = encode_params(&["uint8[]"], &["[1, 2, 3]".to_owned()]).unwrap();
+        let decoded = decode_params(&["uint8[]"], &hex::encode(&calldata)).unwrap();
+        assert_eq!(decoded[0], "[1, 2, 3]");
+    }
+
+    #[test]
+    fn validate_bytes_hex_rejects_odd_length() {
+        assert!(validate_bytes_hex("0x0").is_err());
+        assert!(validate_bytes_hex("0x01").is_ok());
+        assert!(validate_bytes_hex("").is_ok());
+    }
+
+    #[test]
+    fn validate_bytes_hex_rejects_non_hex_digits() {
+        assert!(validate_bytes_hex("0xzz").is_err());
+    }
+
+    #[test]
+    fn parse_abi_entry_function_with_returns() {
+        let entry = parse_abi_entry("function transfer(address to, uint256 amount) returns (bool)").unwrap();
+        assert_eq!(entry.get_field_type(), AbiEntryType::Function);
+        assert_eq!(entry.get_name(), "transfer");
+        assert_eq!(entry_to_method_name(&entry), "transfer(address,uint256)");
+        assert_eq!(entry.get_outputs().len(), 1);
+    }
+
+    #[test]
+    fn parse_abi_entry_event_marks_indexed_args() {
+        let entry =
+            parse_abi_entry("event Transfer(address indexed from, address indexed to, uint256 value)").unwrap();
+        assert_eq!(entry.get_field_type(), AbiEntryType::Event);
+        assert!(entry.get_inputs()[0].get_indexed());
+        assert!(entry.get_inputs()[1].get_indexed());
+        assert!(!entry.get_inputs()[2].get_indexed());
+    }
+
+    #[test]
+    fn parse_abi_entry_constructor_payable() {
+        let entry = parse_abi_entry("constructor(uint256 supply) payable").unwrap();
+        assert_eq!(entry.get_field_type(), AbiEntryType::Constructor);
+        assert!(entry.payable);
+        assert_eq!(entry.get_name(), "");
+    }
+
+    #[test]
+    fn decode_event_splits_indexed_topics_from_data() {
+        let entry =
+            parse_abi_entry("event Transfer(address indexed from, address indexed to, uint256 value)").unwrap();
+        let sig_hash = crypto::keccak256(entry_to_method_name(&entry).as_bytes());
+        let topics = vec![sig_hash.to_vec(), vec![0u8; 32], vec![1u8; 32]];
+        let data = encode(&[Token::Uint(42u64.into())]);
+
+        let result = decode_event(&entry, &topics, &hex::encode(&data)).unwrap();
+
+        assert_eq!(result.len(), 3);
+        assert_eq!(result[0].0, "from");
+        assert_eq!(result[2], ("value".to_owned(), "42".to_owned()));
+    }
+
+    #[test]
+    fn decode_event_rejects_signature_mismatch() {
+        let entry = parse_abi_entry("event Transfer(address indexed from, uint256 value)").unwrap();
+        let topics = vec![vec![0u8; 32], vec![0u8; 32]];
+        let data = encode(&[Token::Uint(1u64.into())]);
+        assert!(decode_event(&entry, &topics, &hex::encode(&data)).is_err());
+    }
+
+    #[test]
+    fn pformat_abi_token_recurses_into_tuples() {
+        let tok = Token::Tuple(vec![Token::Uint(1u64.into()), Token::Bool(true)]);
+        assert_eq!(pformat_abi_token(&tok), "(1, true)");
+    }
+
+    #[test]
+    fn pformat_abi_token_json_renders_structured_values() {
+        let tok = Token::Tuple(vec![Token::Uint(1u64.into()), Token::Bool(true)]);
+        let json = pformat_abi_token_json(&tok);
+        assert_eq!(json["0"], serde_json::json!("1"));
+        assert_eq!(json["1"], serde_json::json!(true));
+    }
+}
@@ -0,0 +1,338 @@
+//! Generates a typed Rust wrapper struct from a smart contract's ABI.
+//!
+//! Rather than hand-building `&[&str]` type/value slices for every call to
+//! `fnhash`/`encode_params`, a contract's JSON ABI can be compiled once into a
+//! checked Rust interface: one method per `Function` entry, with parameters
+//! and return values mapped to concrete Rust types.
+//!
+//! Known deviation: tuple (ABI encoder v2 `struct`) types are generated as
+//! anonymous Rust tuples for method parameters, and as `serde_json::Value`
+//! for return values, rather than as named generated structs. `ParamType`
+//! (and the `SmartContract_ABI_Entry_Param` this module reads types from) do
+//! not carry the per-component field names a named struct would need -- only
+//! the flattened type string (e.g. `(uint256,address)`) is available here --
+//! so a faithful named-struct codegen would require a richer ABI component
+//! representation than this module has access to.
+
+use std::collections::HashMap;
+use std::fmt::Write as FmtWrite;
+
+use ethabi::param_type::ParamType;
+use proto::core::{
+    SmartContract_ABI_Entry as AbiEntry, SmartContract_ABI_Entry_EntryType as AbiEntryType,
+};
+
+use crate::error::Error;
+use crate::utils::abi::read_param_type;
+
+/// Generate the Rust source of a typed wrapper struct named `struct_name` for
+/// the given ABI entries.
+///
+/// Constructor and fallback entries do not produce callable methods: a
+/// constructor has no on-chain selector to call post-deployment, and a
+/// fallback has no fixed signature to bind a method name to.
+pub fn generate_contract(struct_name: &str, entries: &[AbiEntry]) -> Result<String, Error> {
+    let methods = entries
+        .iter()
+        .filter(|entry| entry.get_field_type() == AbiEntryType::Function)
+        .collect::<Vec<_>>();
+
+    let mut method_names = HashMap::new();
+    for entry in &methods {
+        *method_names.entry(entry.get_name().to_owned()).or_insert(0) += 1;
+    }
+
+    let mut out = String::new();
+    writeln!(out, "// This file is auto-generated from a SmartContract ABI. Do not edit by hand.")?;
+    writeln!(out, "pub struct {} {{", struct_name)?;
+    writeln!(out, "    pub address: ::keys::Address,")?;
+    writeln!(out, "}}")?;
+    writeln!(out)?;
+    writeln!(out, "impl {} {{", struct_name)?;
+
+    for entry in &methods {
+        let overloaded = method_names[entry.get_name()] > 1;
+        write_method(&mut out, entry, overloaded)?;
+    }
+
+    writeln!(out, "}}")?;
+    Ok(out)
+}
+
+fn write_method(out: &mut String, entry: &AbiEntry, overloaded: bool) -> Result<(), Error> {
+    let inputs = entry.get_inputs();
+    let outputs = entry.get_outputs();
+
+    let input_types = inputs
+        .iter()
+        .map(|arg| read_param_type(arg.get_field_type()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let output_types = outputs
+        .iter()
+        .map(|arg| read_param_type(arg.get_field_type()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let method_name = abi_name_to_snake_case(entry.get_name(), &input_types, overloaded);
+
+    let arg_names = (0..inputs.len())
+        .map(|i| {
+            if inputs[i].get_name().is_empty() {
+                format!("arg{}", i)
+            } else {
+                abi_name_to_snake_case(inputs[i].get_name(), &[], false)
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let params = arg_names
+        .iter()
+        .zip(input_types.iter())
+        .map(|(name, pt)| format!("{}: {}", name, rust_param_type(pt, false)))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let ret = match output_types.len() {
+        0 => "()".to_owned(),
+        1 => rust_param_type(&output_types[0], true),
+        _ => format!("({})", output_types.iter().map(|pt| rust_param_type(pt, true)).collect::<Vec<_>>().join(", ")),
+    };
+
+    writeln!(out, "    /// `{}`", entry_signature(entry.get_name(), &input_types))?;
+    writeln!(
+        out,
+        "    pub fn {}(&self, {}{}call: impl FnOnce(&[u8]) -> Result<Vec<u8>, crate::error::Error>) -> \
+         Result<{}, crate::error::Error> {{",
+        method_name,
+        params,
+        if params.is_empty() { "" } else { ", " },
+        ret,
+    )?;
+    writeln!(
+        out,
+        "        let fnhash = crate::utils::abi::fnhash(\"{}\");",
+        entry_signature(entry.get_name(), &input_types)
+    )?;
+
+    let input_type_strs = input_types.iter().map(param_type_name).collect::<Vec<_>>();
+    writeln!(
+        out,
+        "        let types: &[&str] = &[{}];",
+        input_type_strs.iter().map(|t| format!("{:?}", t)).collect::<Vec<_>>().join(", ")
+    )?;
+    writeln!(
+        out,
+        "        let values: Vec<String> = vec![{}];",
+        arg_names
+            .iter()
+            .zip(input_types.iter())
+            .map(|(name, pt)| encode_value_expr(name, pt))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )?;
+    writeln!(out, "        let calldata = [&fnhash[..], &crate::utils::abi::encode_params(types, &values)?[..]].concat();")?;
+    writeln!(out, "        let reply = call(&calldata)?;")?;
+
+    if output_types.is_empty() {
+        writeln!(out, "        let _ = reply;")?;
+        writeln!(out, "        Ok(())")?;
+    } else {
+        let output_type_strs = output_types.iter().map(param_type_name).collect::<Vec<_>>();
+        writeln!(
+            out,
+            "        let outputs: &[&str] = &[{}];",
+            output_type_strs.iter().map(|t| format!("{:?}", t)).collect::<Vec<_>>().join(", ")
+        )?;
+        writeln!(
+            out,
+            "        let decoded = crate::utils::abi::decode_params_json(outputs, &::hex::encode(&reply))?;"
+        )?;
+        if output_types.len() == 1 {
+            writeln!(out, "        let mut decoded = decoded.into_iter();")?;
+            writeln!(
+                out,
+                "        let out0 = decoded.next().ok_or_else(|| crate::error::Error::Runtime(\"missing return \
+                 value\".to_owned()))?;"
+            )?;
+            writeln!(out, "        Ok({})", decode_value_expr("out0", &output_types[0]))?;
+        } else {
+            writeln!(out, "        let mut decoded = decoded.into_iter();")?;
+            let mut bindings = Vec::new();
+            for i in 0..output_types.len() {
+                let binding = format!("out{}", i);
+                writeln!(
+                    out,
+                    "        let {} = decoded.next().ok_or_else(|| crate::error::Error::Runtime(\"missing return \
+                     value\".to_owned()))?;",
+                    binding
+                )?;
+                bindings.push(binding);
+            }
+            let tuple_values = bindings
+                .iter()
+                .zip(output_types.iter())
+                .map(|(binding, pt)| decode_value_expr(binding, pt))
+                .collect::<Vec<_>>()
+                .join(", ");
+            writeln!(out, "        Ok(({}))", tuple_values)?;
+        }
+    }
+
+    writeln!(out, "    }}")?;
+    writeln!(out)?;
+    Ok(())
+}
+
+fn entry_signature(name: &str, input_types: &[ParamType]) -> String {
+    format!("{}({})", name, input_types.iter().map(param_type_name).collect::<Vec<_>>().join(","))
+}
+
+fn param_type_name(pt: &ParamType) -> String {
+    format!("{}", pt)
+}
+
+/// Maps a Solidity `ParamType` to the Rust type used in generated bindings.
+///
+/// For `is_output` positions, composite types (arrays/tuples) map to
+/// `serde_json::Value` rather than a concrete Rust container: their values
+/// are decoded via `decode_params_json`, which already recurses into nested
+/// tuples/arrays, so re-parsing that structure back into a bespoke Rust type
+/// at codegen time is avoided.
+fn rust_param_type(pt: &ParamType, is_output: bool) -> String {
+    match pt {
+        ParamType::Address => "::keys::Address".to_owned(),
+        ParamType::Uint(256) => "::num_bigint::BigUint".to_owned(),
+        ParamType::Uint(size) => match size {
+            8 => "u8".to_owned(),
+            16 => "u16".to_owned(),
+            32 => "u32".to_owned(),
+            64 => "u64".to_owned(),
+            128 => "u128".to_owned(),
+            _ => "::num_bigint::BigUint".to_owned(),
+        },
+        ParamType::Int(size) => match size {
+            8 => "i8".to_owned(),
+            16 => "i16".to_owned(),
+            32 => "i32".to_owned(),
+            64 => "i64".to_owned(),
+            128 => "i128".to_owned(),
+            _ => "::num_bigint::BigInt".to_owned(),
+        },
+        ParamType::Bool => "bool".to_owned(),
+        ParamType::String => "String".to_owned(),
+        ParamType::Bytes => "Vec<u8>".to_owned(),
+        ParamType::FixedBytes(size) => format!("[u8; {}]", size),
+        ParamType::Array(..) | ParamType::FixedArray(..) | ParamType::Tuple(..) if is_output => {
+            "::serde_json::Value".to_owned()
+        }
+        ParamType::Array(inner) => format!("Vec<{}>", rust_param_type(inner, is_output)),
+        ParamType::FixedArray(inner, size) => format!("[{}; {}]", rust_param_type(inner, is_output), size),
+        ParamType::Tuple(members) => {
+            format!("({})", members.iter().map(|m| rust_param_type(m, is_output)).collect::<Vec<_>>().join(", "))
+        }
+    }
+}
+
+/// Builds a Rust expression of type `String` that encodes `var` (of type
+/// `rust_param_type(pt, false)`) the way `encode_params`/`LenientTokenizer`
+/// expect: numeric/bool/string values via `Display`, addresses via
+/// `abi::address_to_abi_hex` (an `::keys::Address`'s `Display` impl renders
+/// base58, not the hex ethabi's `ParamType::Address` tokenizer expects), and
+/// bytes/arrays/tuples via an explicit hex or bracketed rendering, since
+/// those Rust types don't implement `Display` either.
+fn encode_value_expr(var: &str, pt: &ParamType) -> String {
+    match pt {
+        ParamType::Address => format!("crate::utils::abi::address_to_abi_hex(&{})", var),
+        ParamType::Bytes => format!("format!(\"0x{{}}\", ::hex::encode(&{}))", var),
+        ParamType::FixedBytes(_) => format!("format!(\"0x{{}}\", ::hex::encode(&{}[..]))", var),
+        ParamType::Array(inner) | ParamType::FixedArray(inner, _) => {
+            let elem = encode_value_expr("item", inner);
+            format!(
+                "format!(\"[{{}}]\", {}.iter().map(|item| {}).collect::<Vec<_>>().join(\", \"))",
+                var, elem
+            )
+        }
+        ParamType::Tuple(members) => {
+            let fields = members
+                .iter()
+                .enumerate()
+                .map(|(i, m)| encode_value_expr(&format!("{}.{}", var, i), m))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("format!(\"({{}})\", vec![{}].join(\", \"))", fields)
+        }
+        _ => format!("{}.to_string()", var),
+    }
+}
+
+/// Builds a Rust expression of type `rust_param_type(pt, true)` that extracts
+/// a typed value out of `var` (a `serde_json::Value` produced by
+/// `decode_params_json`/`pformat_abi_token_json`).
+fn decode_value_expr(var: &str, pt: &ParamType) -> String {
+    let as_str = format!(
+        "{}.as_str().ok_or_else(|| crate::error::Error::Runtime(\"expected a JSON string\".to_owned()))?",
+        var
+    );
+    match pt {
+        ParamType::Address => format!(
+            "{}.parse::<::keys::Address>().map_err(|e| crate::error::Error::Runtime(e.to_string()))?",
+            as_str
+        ),
+        ParamType::Uint(256) => format!(
+            "{}.parse::<::num_bigint::BigUint>().map_err(|e| crate::error::Error::Runtime(e.to_string()))?",
+            as_str
+        ),
+        ParamType::Int(size) if *size > 128 => format!(
+            "{}.parse::<::num_bigint::BigInt>().map_err(|e| crate::error::Error::Runtime(e.to_string()))?",
+            as_str
+        ),
+        ParamType::Uint(_) | ParamType::Int(_) => format!(
+            "{}.parse::<{}>().map_err(|e| crate::error::Error::Runtime(e.to_string()))?",
+            as_str,
+            rust_param_type(pt, true)
+        ),
+        ParamType::Bool => format!(
+            "{}.as_bool().ok_or_else(|| crate::error::Error::Runtime(\"expected a JSON bool\".to_owned()))?",
+            var
+        ),
+        ParamType::String => format!("{}.to_owned()", as_str),
+        ParamType::Bytes => format!(
+            "::hex::decode({}.trim_start_matches(\"0x\")).map_err(|e| crate::error::Error::Runtime(e.to_string()))?",
+            as_str
+        ),
+        ParamType::FixedBytes(size) => format!(
+            "::hex::decode({}.trim_start_matches(\"0x\")).map_err(|e| crate::error::Error::Runtime(e.to_string()))?\
+             .try_into().map_err(|_| crate::error::Error::Runtime(\"wrong fixed bytes length, expected {}\".to_owned()))?",
+            as_str, size
+        ),
+        ParamType::Array(..) | ParamType::FixedArray(..) | ParamType::Tuple(..) => format!("{}.clone()", var),
+    }
+}
+
+/// Converts an ABI identifier (`camelCase` or already `snake_case`) to
+/// `snake_case`, appending the input-type signature when `disambiguate` is
+/// set to resolve an overloaded method name.
+fn abi_name_to_snake_case(name: &str, input_types: &[ParamType], disambiguate: bool) -> String {
+    let mut out = String::new();
+    for (i, ch) in name.chars().enumerate() {
+        if ch.is_uppercase() {
+            if i != 0 {
+                out.push('_');
+            }
+            out.extend(ch.to_lowercase());
+        } else {
+            out.push(ch);
+        }
+    }
+    if disambiguate {
+        let suffix = input_types
+            .iter()
+            .map(param_type_name)
+            .collect::<Vec<_>>()
+            .join("_")
+            .replace(['(', ')', ','], "_");
+        if !suffix.is_empty() {
+            write!(out, "_{}", suffix).ok();
+        }
+    }
+    out
+}